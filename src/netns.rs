@@ -1,3 +1,4 @@
+use std::ffi::CString;
 use std::fmt::{Display, Formatter};
 use std::fs::{DirBuilder, File, OpenOptions};
 use std::os::fd::{AsFd, AsRawFd, RawFd};
@@ -5,23 +6,92 @@ use std::os::unix::fs::{DirBuilderExt, MetadataExt, OpenOptionsExt};
 use std::path::{Path, PathBuf};
 
 use nix::sched::CloneFlags;
+use nix::sys::wait::WaitStatus;
+use nix::unistd::{ForkResult, Pid};
+
+use anyhow::bail;
 
 pub const BIND_MOUNT_PATH: &str = "/run/netns";
 
+/// The kind of Linux namespace a [`Namespace`] refers to.
+///
+/// Each variant knows the `CLONE_NEW*` flag passed to `unshare`/`setns`, the
+/// `/proc/<pid>/task/<tid>/ns/<name>` entry used to open it, and the directory
+/// under `/run` where named namespaces of that kind are bind-mount pinned.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NsKind {
+    Net,
+    User,
+    Mount,
+    Pid,
+    Uts,
+    Ipc,
+    Cgroup,
+}
+
+impl NsKind {
+    /// The `CLONE_NEW*` flag matching this namespace kind.
+    pub fn flag(self) -> CloneFlags {
+        match self {
+            NsKind::Net => CloneFlags::CLONE_NEWNET,
+            NsKind::User => CloneFlags::CLONE_NEWUSER,
+            NsKind::Mount => CloneFlags::CLONE_NEWNS,
+            NsKind::Pid => CloneFlags::CLONE_NEWPID,
+            NsKind::Uts => CloneFlags::CLONE_NEWUTS,
+            NsKind::Ipc => CloneFlags::CLONE_NEWIPC,
+            NsKind::Cgroup => CloneFlags::CLONE_NEWCGROUP,
+        }
+    }
+
+    /// The `/proc/.../ns/<name>` file name for this kind.
+    pub fn proc_name(self) -> &'static str {
+        match self {
+            NsKind::Net => "net",
+            NsKind::User => "user",
+            NsKind::Mount => "mnt",
+            NsKind::Pid => "pid",
+            NsKind::Uts => "uts",
+            NsKind::Ipc => "ipc",
+            NsKind::Cgroup => "cgroup",
+        }
+    }
+
+    /// The directory under which named namespaces of this kind are pinned.
+    pub fn bind_mount_path(self) -> &'static str {
+        match self {
+            NsKind::Net => BIND_MOUNT_PATH,
+            NsKind::User => "/run/userns",
+            NsKind::Mount => "/run/mntns",
+            NsKind::Pid => "/run/pidns",
+            NsKind::Uts => "/run/utsns",
+            NsKind::Ipc => "/run/ipcns",
+            NsKind::Cgroup => "/run/cgroupns",
+        }
+    }
+}
+
+/// A handle to a single Linux namespace of some [`NsKind`].
+///
+/// `Namespace` is the generalised form of [`Netns`]: it works uniformly across
+/// net/user/mount/pid/uts/ipc/cgroup namespaces so a caller can, for example,
+/// open and enter a mount+net pair. The held `File` keeps the namespace alive
+/// and is what `setns` is called against.
 #[derive(Debug)]
-pub struct Netns {
+pub struct Namespace {
     f: File,
+    flags: CloneFlags,
+    kind: NsKind,
     path: Option<PathBuf>,
 }
 
-impl Netns {
-    pub fn new() -> anyhow::Result<Self> {
-        nix::sched::unshare(CloneFlags::CLONE_NEWNET)?;
-        Self::get()
+impl Namespace {
+    pub fn new(kind: NsKind) -> anyhow::Result<Self> {
+        nix::sched::unshare(kind.flag())?;
+        Self::get(kind)
     }
 
-    pub fn new_named(name: &str) -> anyhow::Result<Self> {
-        let bind_mount_path: &Path = BIND_MOUNT_PATH.as_ref();
+    pub fn new_named(kind: NsKind, name: &str) -> anyhow::Result<Self> {
+        let bind_mount_path: &Path = kind.bind_mount_path().as_ref();
         if !bind_mount_path.exists() {
             DirBuilder::new().mode(0o755).recursive(true).create(bind_mount_path)?;
         }
@@ -34,8 +104,13 @@ impl Netns {
             .mode(0o444)
             .open(&named_path)?;
 
-        let new_ns = Self::new()?;
-        let ns_path = format!("/proc/{}/task/{}/ns/net", std::process::id(), nix::unistd::gettid());
+        let new_ns = Self::new(kind)?;
+        let ns_path = format!(
+            "/proc/{}/task/{}/ns/{}",
+            std::process::id(),
+            nix::unistd::gettid(),
+            kind.proc_name()
+        );
         nix::mount::mount(
             Some(Path::new(&ns_path)),
             Path::new(&named_path),
@@ -45,8 +120,8 @@ impl Netns {
         )?;
         return Ok(new_ns);
     }
-    pub fn delete_named(name: &str) -> anyhow::Result<()> {
-        let path: &Path = BIND_MOUNT_PATH.as_ref();
+    pub fn delete_named(kind: NsKind, name: &str) -> anyhow::Result<()> {
+        let path: &Path = kind.bind_mount_path().as_ref();
         let named_path = path.join(name);
         if !named_path.exists() {
             return Ok(());
@@ -55,26 +130,42 @@ impl Netns {
         std::fs::remove_file(named_path)?;
         Ok(())
     }
-    pub fn get_from_path(path: &Path) -> anyhow::Result<Option<Self>> {
+    pub fn get_from_path(kind: NsKind, path: &Path) -> anyhow::Result<Option<Self>> {
         let file = OpenOptions::new().read(true).open(&path).ok();
         match file {
             None => Ok(None),
-            Some(file) => Ok(Some(Self { f: file, path: Some(path.to_path_buf()) })),
+            Some(file) => {
+                Ok(Some(Self { f: file, flags: kind.flag(), kind, path: Some(path.to_path_buf()) }))
+            }
         }
     }
-    pub fn get_from_name(name: &str) -> anyhow::Result<Option<Self>> {
-        let path: &Path = BIND_MOUNT_PATH.as_ref();
+    pub fn get_from_name(kind: NsKind, name: &str) -> anyhow::Result<Option<Self>> {
+        let path: &Path = kind.bind_mount_path().as_ref();
         let named_path = path.join(name);
-        Self::get_from_path(&named_path)
+        Self::get_from_path(kind, &named_path)
     }
 
-    pub fn get() -> anyhow::Result<Self> {
-        let ns_path = format!("/proc/{}/task/{}/ns/net", std::process::id(), nix::unistd::gettid());
+    pub fn get(kind: NsKind) -> anyhow::Result<Self> {
+        let ns_path = format!(
+            "/proc/{}/task/{}/ns/{}",
+            std::process::id(),
+            nix::unistd::gettid(),
+            kind.proc_name()
+        );
         let file = OpenOptions::new().read(true).open(Path::new(&ns_path))?;
-        Ok(Self { f: file, path: None })
+        Ok(Self { f: file, flags: kind.flag(), kind, path: None })
     }
     pub fn set(&self) -> anyhow::Result<()> {
-        Ok(nix::sched::setns(self.f.as_fd(), CloneFlags::CLONE_NEWNET)?)
+        Ok(nix::sched::setns(self.f.as_fd(), self.flags)?)
+    }
+    pub fn kind(&self) -> NsKind {
+        self.kind
+    }
+    /// The `(dev, ino)` pair that uniquely identifies the open namespace, or
+    /// `None` if the backing file can no longer be stat'd. This is the identity
+    /// used by both [`PartialEq`] and [`unique_id`](Self::unique_id).
+    fn identity(&self) -> Option<(u64, u64)> {
+        self.f.metadata().ok().map(|m| (m.dev(), m.ino()))
     }
     pub fn unique_id(&self) -> String {
         match self.f.metadata() {
@@ -93,6 +184,357 @@ impl Netns {
         self.path.clone()
     }
 }
+
+/// A network namespace: a [`Namespace`] specialised to [`NsKind::Net`].
+///
+/// This is a thin wrapper that preserves the argument-free net-only API; it
+/// derefs to [`Namespace`] so `set`, `fd`, `unique_id`, `path` and friends are
+/// available directly.
+#[derive(Debug)]
+pub struct Netns(Namespace);
+
+impl Netns {
+    pub fn new() -> anyhow::Result<Self> {
+        Ok(Self(Namespace::new(NsKind::Net)?))
+    }
+
+    pub fn new_named(name: &str) -> anyhow::Result<Self> {
+        Ok(Self(Namespace::new_named(NsKind::Net, name)?))
+    }
+
+    pub fn delete_named(name: &str) -> anyhow::Result<()> {
+        Namespace::delete_named(NsKind::Net, name)
+    }
+
+    pub fn get_from_path(path: &Path) -> anyhow::Result<Option<Self>> {
+        Ok(Namespace::get_from_path(NsKind::Net, path)?.map(Self))
+    }
+
+    pub fn get_from_name(name: &str) -> anyhow::Result<Option<Self>> {
+        Ok(Namespace::get_from_name(NsKind::Net, name)?.map(Self))
+    }
+
+    pub fn get() -> anyhow::Result<Self> {
+        Ok(Self(Namespace::get(NsKind::Net)?))
+    }
+}
+
+impl Netns {
+    /// Build a command to run an external program inside this namespace, in the
+    /// spirit of `ip netns exec`. The child `fork`s, enters this (and any extra)
+    /// namespace via `setns` and then `execvp`s the program; the parent reaps it.
+    pub fn command(&self, program: &str) -> NsCommand<'_> {
+        NsCommand::new(self, program)
+    }
+
+    /// Run `f` inside this namespace on a dedicated throwaway thread.
+    ///
+    /// `setns` is issued only on the spawned thread, so the caller's own thread
+    /// namespace is left untouched regardless of whether `f` returns normally,
+    /// panics, or early-returns. The child thread's result is returned once it
+    /// is joined.
+    pub fn run_in<T: Send>(&self, f: impl FnOnce() -> T + Send) -> anyhow::Result<T> {
+        let joined = std::thread::scope(|scope| {
+            scope
+                .spawn(|| -> anyhow::Result<T> {
+                    self.set()?;
+                    Ok(f())
+                })
+                .join()
+        });
+        match joined {
+            Ok(result) => result,
+            Err(_) => bail!("thread running in namespace panicked"),
+        }
+    }
+
+    /// Enter this namespace on the current thread, returning an [`NsGuard`] that
+    /// restores the previous namespace when dropped — a panic-safe replacement
+    /// for the `exec_netns!` macro.
+    pub fn enter(&self) -> anyhow::Result<NsGuard> {
+        let prev = Netns::get()?;
+        self.set()?;
+        Ok(NsGuard { prev })
+    }
+
+    /// List the named network namespaces under [`BIND_MOUNT_PATH`], the
+    /// equivalent of `ip netns list`. Each entry is paired with an open handle.
+    pub fn list() -> anyhow::Result<Vec<(String, Netns)>> {
+        let dir: &Path = BIND_MOUNT_PATH.as_ref();
+        let mut out = Vec::new();
+        if !dir.exists() {
+            return Ok(out);
+        }
+        for entry in std::fs::read_dir(dir)? {
+            let entry = entry?;
+            if let Some(ns) = Netns::get_from_path(&entry.path())? {
+                out.push((entry.file_name().to_string_lossy().into_owned(), ns));
+            }
+        }
+        Ok(out)
+    }
+
+    /// Spawn a fresh process directly into brand-new namespaces with `clone`,
+    /// rather than `unshare`-ing (and thereby contaminating) the caller.
+    ///
+    /// The requested `CLONE_NEW*` flags are passed straight to `clone`, so the
+    /// child begins life already isolated. The returned [`NsChild`] owns the
+    /// child's stack, can reap it, and can pin its new network namespace so the
+    /// parent keeps configuring it after the fork.
+    pub fn spawn_new(
+        flags: CloneFlags,
+        child: impl FnOnce() -> i32,
+    ) -> anyhow::Result<NsChild> {
+        let mut stack = Stack::new();
+        let mut child = Some(child);
+        let cb = Box::new(move || {
+            let child = child.take().expect("clone callback invoked more than once");
+            child() as isize
+        });
+        let pid = unsafe {
+            nix::sched::clone(cb, stack.as_mut_slice(), flags, Some(nix::libc::SIGCHLD))?
+        };
+        Ok(NsChild { pid, _stack: stack })
+    }
+
+    /// Return the PIDs of every process currently joined to this namespace, the
+    /// equivalent of `ip netns pids <name>`.
+    ///
+    /// The PIDs are the top-level numeric entries of `/proc`; each process's
+    /// `ns/net` link is compared against this namespace's `(dev, ino)` identity.
+    pub fn processes(&self) -> anyhow::Result<Vec<Pid>> {
+        let target = self.identity();
+        let mut pids = Vec::new();
+        for entry in std::fs::read_dir("/proc")?.flatten() {
+            let file_name = entry.file_name();
+            let pid: i32 = match file_name.to_string_lossy().parse() {
+                Ok(pid) => pid,
+                Err(_) => continue,
+            };
+            let ns_path = entry.path().join("ns").join("net");
+            if let Ok(meta) = std::fs::metadata(&ns_path) {
+                if Some((meta.dev(), meta.ino())) == target {
+                    pids.push(Pid::from_raw(pid));
+                }
+            }
+        }
+        Ok(pids)
+    }
+}
+
+/// A heap-allocated child stack for `clone`, mirroring rebel-runner's
+/// `util/stack.rs`. The backing memory must outlive the cloned child, so the
+/// owning [`NsChild`] holds the `Stack` for as long as the `NsChild` lives;
+/// dropping the `NsChild` frees the stack whether or not the child has exited.
+struct Stack {
+    mem: Vec<u8>,
+}
+
+impl Stack {
+    const DEFAULT_SIZE: usize = 1024 * 1024;
+
+    fn new() -> Self {
+        Self { mem: vec![0u8; Self::DEFAULT_SIZE] }
+    }
+
+    fn as_mut_slice(&mut self) -> &mut [u8] {
+        &mut self.mem
+    }
+}
+
+/// A child spawned by [`Netns::spawn_new`].
+///
+/// Owns the child's `clone` stack (which must not be freed while the child
+/// runs), reaps it via [`NsChild::wait`], and can pin its freshly created
+/// network namespace with [`NsChild::pin`].
+pub struct NsChild {
+    pid: Pid,
+    _stack: Stack,
+}
+
+impl NsChild {
+    /// The child's PID.
+    pub fn pid(&self) -> Pid {
+        self.pid
+    }
+
+    /// Pin the child's new network namespace by bind-mounting
+    /// `/proc/<pid>/ns/net` under [`BIND_MOUNT_PATH`], returning a handle the
+    /// parent can keep configuring while the child is still alive (before it
+    /// execs its payload).
+    pub fn pin(&self, name: &str) -> anyhow::Result<Netns> {
+        let bind_mount_path: &Path = BIND_MOUNT_PATH.as_ref();
+        if !bind_mount_path.exists() {
+            DirBuilder::new().mode(0o755).recursive(true).create(bind_mount_path)?;
+        }
+        let named_path = bind_mount_path.join(name);
+        let _ = OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .mode(0o444)
+            .open(&named_path)?;
+        let ns_path = format!("/proc/{}/ns/net", self.pid.as_raw());
+        nix::mount::mount(
+            Some(Path::new(&ns_path)),
+            Path::new(&named_path),
+            None::<&str>,
+            nix::mount::MsFlags::MS_BIND,
+            None::<&str>,
+        )?;
+        Netns::get_from_path(&named_path)?
+            .ok_or_else(|| anyhow::anyhow!("failed to open pinned namespace {}", name))
+    }
+
+    /// Wait for the child to exit and convert its status via [`Checkable`].
+    pub fn wait(&self) -> anyhow::Result<()> {
+        nix::sys::wait::waitpid(self.pid, None)?.check()
+    }
+}
+
+/// RAII guard that restores the thread's original network namespace on drop.
+///
+/// Created by [`Netns::enter`]; the namespace captured at creation time is
+/// re-entered in [`Drop`], so a scope that enters another namespace cannot leave
+/// the thread stranded even if it unwinds.
+pub struct NsGuard {
+    prev: Netns,
+}
+
+impl Drop for NsGuard {
+    fn drop(&mut self) {
+        let _ = self.prev.set();
+    }
+}
+
+/// A type whose completion status can be turned into a `Result`.
+///
+/// Implemented for both [`std::process::ExitStatus`] and
+/// [`nix::sys::wait::WaitStatus`] so callers get a typed error instead of
+/// having to inspect raw wait codes.
+pub trait Checkable {
+    /// `Ok(())` on a clean zero exit, otherwise an error describing how the
+    /// process ended.
+    fn check(&self) -> anyhow::Result<()>;
+}
+
+impl Checkable for std::process::ExitStatus {
+    fn check(&self) -> anyhow::Result<()> {
+        if self.success() {
+            return Ok(());
+        }
+        match self.code() {
+            Some(code) => bail!("Process exited with exit code: {}", code),
+            None => {
+                use std::os::unix::process::ExitStatusExt;
+                match self.signal() {
+                    Some(sig) => bail!("Process terminated by signal: {}", sig),
+                    None => bail!("Process exited abnormally"),
+                }
+            }
+        }
+    }
+}
+
+impl Checkable for WaitStatus {
+    fn check(&self) -> anyhow::Result<()> {
+        match self {
+            WaitStatus::Exited(_, 0) => Ok(()),
+            WaitStatus::Exited(_, code) => bail!("Process exited with exit code: {}", code),
+            WaitStatus::Signaled(_, sig, _) => bail!("Process terminated by signal: {}", sig),
+            other => bail!("Process exited abnormally: {:?}", other),
+        }
+    }
+}
+
+/// A builder for running an external program inside a [`Netns`].
+///
+/// Configure args, environment and any additional namespaces to enter, then
+/// call [`NsCommand::status`] to fork/exec the program and wait for it.
+pub struct NsCommand<'a> {
+    ns: &'a Netns,
+    program: CString,
+    args: Vec<CString>,
+    env: Option<Vec<CString>>,
+    extra: Vec<&'a Namespace>,
+}
+
+impl<'a> NsCommand<'a> {
+    fn new(ns: &'a Netns, program: &str) -> Self {
+        let program = CString::new(program).expect("program name contains a nul byte");
+        let args = vec![program.clone()];
+        Self { ns, program, args, env: None, extra: Vec::new() }
+    }
+
+    /// Append a single argument (after the implicit `argv[0]`).
+    pub fn arg(&mut self, arg: &str) -> &mut Self {
+        self.args.push(CString::new(arg).expect("argument contains a nul byte"));
+        self
+    }
+
+    /// Append several arguments at once.
+    pub fn args<I: IntoIterator<Item = S>, S: AsRef<str>>(&mut self, args: I) -> &mut Self {
+        for arg in args {
+            self.arg(arg.as_ref());
+        }
+        self
+    }
+
+    /// Set an environment variable for the child. Calling this at least once
+    /// switches the child to an explicit (non-inherited) environment.
+    pub fn env(&mut self, key: &str, value: &str) -> &mut Self {
+        let entry = CString::new(format!("{}={}", key, value)).expect("env entry contains a nul byte");
+        self.env.get_or_insert_with(Vec::new).push(entry);
+        self
+    }
+
+    /// Enter an additional namespace (e.g. a mount namespace) before exec.
+    pub fn namespace(&mut self, ns: &'a Namespace) -> &mut Self {
+        self.extra.push(ns);
+        self
+    }
+
+    /// Fork a child into the configured namespaces, exec the program and wait
+    /// for it. The returned `Result` is the child's status run through
+    /// [`Checkable::check`], so a non-zero exit is an `Err`.
+    pub fn status(&self) -> anyhow::Result<()> {
+        match unsafe { nix::unistd::fork()? } {
+            ForkResult::Child => {
+                // Only reached if entering the namespaces or exec fails.
+                if let Err(e) = self.exec_child() {
+                    eprintln!("netns command failed: {}", e);
+                }
+                unsafe { nix::libc::_exit(127) };
+            }
+            ForkResult::Parent { child } => {
+                let status = nix::sys::wait::waitpid(child, None)?;
+                status.check()
+            }
+        }
+    }
+
+    fn exec_child(&self) -> anyhow::Result<std::convert::Infallible> {
+        for ns in &self.extra {
+            ns.set()?;
+        }
+        self.ns.set()?;
+        match &self.env {
+            Some(env) => {
+                nix::unistd::execvpe(&self.program, &self.args, env)?;
+            }
+            None => {
+                nix::unistd::execvp(&self.program, &self.args)?;
+            }
+        }
+        unreachable!("execvp returns only on error")
+    }
+}
+
+impl std::ops::Deref for Netns {
+    type Target = Namespace;
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
 #[macro_export]
 macro_rules! exec_netns {
     ($cur_ns:expr, $target_ns:expr, $result:ident, $exec:expr) => {
@@ -102,23 +544,19 @@ macro_rules! exec_netns {
     };
 }
 
-impl PartialEq<Self> for Netns {
+impl PartialEq<Self> for Namespace {
     fn eq(&self, other: &Self) -> bool {
         if std::ptr::eq(self, other) {
             return true;
         }
-        let self_meta = self.f.metadata();
-        let other_meta = other.f.metadata();
-        if self_meta.is_err() || other_meta.is_err() {
-            return false;
+        match (self.identity(), other.identity()) {
+            (Some(a), Some(b)) => a == b,
+            _ => false,
         }
-        let self_meta = self_meta.unwrap();
-        let other_meta = other_meta.unwrap();
-        return self_meta.dev() == other_meta.dev() && self_meta.ino() == other_meta.ino();
     }
 }
 
-impl Display for Netns {
+impl Display for Namespace {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         match self.f.metadata() {
             Err(_) => {
@@ -131,6 +569,20 @@ impl Display for Netns {
     }
 }
 
+impl Eq for Namespace {}
+
+impl PartialEq<Self> for Netns {
+    fn eq(&self, other: &Self) -> bool {
+        self.0 == other.0
+    }
+}
+
+impl Display for Netns {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        Display::fmt(&self.0, f)
+    }
+}
+
 impl Eq for Netns {}
 
 
@@ -209,6 +661,30 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_namespace_kinds() {
+        assert_eq!(NsKind::Net.proc_name(), "net");
+        assert_eq!(NsKind::Mount.proc_name(), "mnt");
+        assert_eq!(NsKind::Net.flag(), CloneFlags::CLONE_NEWNET);
+        assert_eq!(NsKind::Mount.flag(), CloneFlags::CLONE_NEWNS);
+        assert_eq!(NsKind::Net.bind_mount_path(), BIND_MOUNT_PATH);
+    }
+
+    #[test]
+    fn test_checkable_wait_status() {
+        use nix::sys::signal::Signal;
+        use nix::unistd::Pid;
+
+        let pid = Pid::from_raw(1);
+        assert!(WaitStatus::Exited(pid, 0).check().is_ok());
+
+        let err = WaitStatus::Exited(pid, 3).check().unwrap_err();
+        assert_eq!(err.to_string(), "Process exited with exit code: 3");
+
+        let err = WaitStatus::Signaled(pid, Signal::SIGKILL, false).check().unwrap_err();
+        assert_eq!(err.to_string(), "Process terminated by signal: SIGKILL");
+    }
+
     fn foo() -> anyhow::Result<()> {
         bail!("me..........")
     }
@@ -216,4 +692,4 @@ mod tests {
     fn bar() -> anyhow::Result<()> {
         bail!("me..........")
     }
-}
\ No newline at end of file
+}